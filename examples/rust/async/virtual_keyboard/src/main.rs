@@ -1,176 +1,288 @@
-use tokio::time::{sleep, Duration};
-use viiper_client::{AsyncViiperClient, devices::keyboard::*};
-
-#[tokio::main]
-async fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <api_addr>", args[0]);
-        eprintln!("Example: {} localhost:3242", args[0]);
-        std::process::exit(1);
-    }
-
-    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
-        eprintln!("Invalid address '{}': {}", args[1], e);
-        std::process::exit(1);
-    });
-    
-    let client = AsyncViiperClient::new(addr);
-
-    // Find or create a bus
-    let (bus_id, created_bus) = match client.bus_list().await {
-        Ok(resp) if resp.buses.is_empty() => {
-            match client.bus_create(None).await {
-                Ok(r) => {
-                    println!("Created bus {}", r.bus_id);
-                    (r.bus_id, true)
-                }
-                Err(e) => {
-                    eprintln!("BusCreate failed: {}", e);
-                    std::process::exit(1);
-                }
-            }
-        }
-        Ok(resp) => {
-            let bus_id = *resp.buses.iter().min().unwrap();
-            println!("Using existing bus {}", bus_id);
-            (bus_id, false)
-        }
-        Err(e) => {
-            eprintln!("BusList error: {}", e);
-            std::process::exit(1);
-        }
-    };
-
-    // Add device
-    let device_info = match client.bus_device_add(bus_id, &viiper_client::types::DeviceCreateRequest {
-        r#type: Some("keyboard".to_string()),
-        id_vendor: None,
-        id_product: None,
-    }).await {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("AddDevice error: {}", e);
-            if created_bus {
-                let _ = client.bus_remove(Some(bus_id)).await;
-            }
-            std::process::exit(1);
-        }
-    };
-
-    // Connect to device stream
-    let mut stream = match client.connect_device(device_info.bus_id, &device_info.dev_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("ConnectDevice error: {}", e);
-            let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
-            if created_bus {
-                let _ = client.bus_remove(Some(bus_id)).await;
-            }
-            std::process::exit(1);
-        }
-    };
-
-    println!("Created and connected to device {} on bus {}", device_info.dev_id, device_info.bus_id);
-
-    stream.on_disconnect(|| {
-        eprintln!("Device disconnected by server");
-        std::process::exit(0);
-    }).expect("Failed to register disconnect callback");
-
-    stream.on_output(|stream| async move {
-        use tokio::io::AsyncReadExt;
-        let mut buf = [0u8; OUTPUT_SIZE];
-        let mut guard = stream.lock().await;
-        guard.read_exact(&mut buf).await?;
-        drop(guard);
-        let leds = buf[0];
-        let num_lock = (leds & 0x01) != 0;
-        let caps_lock = (leds & 0x02) != 0;
-        let scroll_lock = (leds & 0x04) != 0;
-        let compose = (leds & 0x08) != 0;
-        let kana = (leds & 0x10) != 0;
-        println!("← LEDs: Num={} Caps={} Scroll={} Compose={} Kana={}", num_lock, caps_lock, scroll_lock, compose, kana);
-        Ok(())
-    }).expect("Failed to register LED callback");
-
-    println!("Every 5s: type 'Hello!' + Enter. Press Ctrl+C to stop.");
-
-    // Type "Hello!" + Enter every 5 seconds
-    let mut interval = tokio::time::interval(Duration::from_secs(5));
-    loop {
-        interval.tick().await;
-
-        if let Err(e) = type_string(&mut stream, "Hello!").await {
-            eprintln!("Write error: {}", e);
-            break;
-        }
-
-        sleep(Duration::from_millis(100)).await;
-
-        if let Err(e) = press_key(&mut stream, KEY_ENTER).await {
-            eprintln!("Write error: {}", e);
-            break;
-        }
-
-        println!("→ Typed: Hello!");
-    }
-
-    // Cleanup
-    let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
-    if created_bus {
-        let _ = client.bus_remove(Some(bus_id)).await;
-    }
-}
-
-async fn type_string(stream: &mut viiper_client::AsyncDeviceStream, text: &str) -> Result<(), viiper_client::error::ViiperError> {
-    for ch in text.chars() {
-        let code_point = ch as u32;
-        let key = match CHAR_TO_KEY.get(&(code_point as u8)) {
-            Some(&k) => k,
-            None => continue,
-        };
-
-        let mut mods = 0;
-        if SHIFT_CHARS.contains(&(code_point as u8)) {
-            mods = MOD_LEFT_SHIFT;
-        }
-
-        // Key down
-        let down = KeyboardInput {
-            modifiers: mods,
-            count: 1,
-            keys: vec![key],
-        };
-        stream.send(&down).await?;
-        sleep(Duration::from_millis(100)).await;
-
-        // Key up
-        let up = KeyboardInput {
-            modifiers: 0,
-            count: 0,
-            keys: vec![],
-        };
-        stream.send(&up).await?;
-        sleep(Duration::from_millis(100)).await;
-    }
-    Ok(())
-}
-
-async fn press_key(stream: &mut viiper_client::AsyncDeviceStream, key: u8) -> Result<(), viiper_client::error::ViiperError> {
-    let press = KeyboardInput {
-        modifiers: 0,
-        count: 1,
-        keys: vec![key],
-    };
-    stream.send(&press).await?;
-    sleep(Duration::from_millis(100)).await;
-
-    let release = KeyboardInput {
-        modifiers: 0,
-        count: 0,
-        keys: vec![],
-    };
-    stream.send(&release).await?;
-    Ok(())
-}
+mod keymap;
+mod recorder;
+mod schedule;
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use viiper_client::{AsyncViiperClient, devices::keyboard::*};
+
+use keymap::Keymap;
+use recorder::Recorder;
+use schedule::ScheduleQueue;
+
+const DEVICE_TYPE: &str = "keyboard";
+
+/// Decoded form of the LED output report, recorded alongside the keys
+/// sent to the device, instead of only ever being printed. Replay skips
+/// these entries.
+#[derive(Serialize, Deserialize)]
+struct LedState {
+    num_lock: bool,
+    caps_lock: bool,
+    scroll_lock: bool,
+    compose: bool,
+    kana: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <api_addr> [--record <file> | --replay <file> [speed]] [--layout us|us-intl|de]", args[0]);
+        eprintln!("Example: {} localhost:3242 --record session.viiper", args[0]);
+        std::process::exit(1);
+    }
+
+    let record_path = match args.get(2).map(String::as_str) {
+        Some("--record") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--record requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_path = match args.get(2).map(String::as_str) {
+        Some("--replay") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--replay requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_speed: f64 = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(1.0);
+
+    let keymap = match args
+        .iter()
+        .position(|a| a == "--layout")
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+    {
+        Some("us-intl") => Keymap::us_international(),
+        Some("de") => Keymap::de_qwertz(),
+        Some("us") | None => Keymap::us_qwerty(),
+        Some(other) => {
+            eprintln!("Unknown layout '{}', expected one of: us, us-intl, de", other);
+            std::process::exit(1);
+        }
+    };
+
+    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
+        eprintln!("Invalid address '{}': {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    let client = AsyncViiperClient::new(addr);
+
+    // Find or create a bus
+    let (bus_id, created_bus) = match client.bus_list().await {
+        Ok(resp) if resp.buses.is_empty() => {
+            match client.bus_create(None).await {
+                Ok(r) => {
+                    println!("Created bus {}", r.bus_id);
+                    (r.bus_id, true)
+                }
+                Err(e) => {
+                    eprintln!("BusCreate failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Ok(resp) => {
+            let bus_id = *resp.buses.iter().min().unwrap();
+            println!("Using existing bus {}", bus_id);
+            (bus_id, false)
+        }
+        Err(e) => {
+            eprintln!("BusList error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Add device
+    let device_info = match client.bus_device_add(bus_id, &viiper_client::types::DeviceCreateRequest {
+        r#type: Some("keyboard".to_string()),
+        id_vendor: None,
+        id_product: None,
+    }).await {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("AddDevice error: {}", e);
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id)).await;
+            }
+            std::process::exit(1);
+        }
+    };
+
+    // Connect to device stream
+    let mut stream = match client.connect_device(device_info.bus_id, &device_info.dev_id).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ConnectDevice error: {}", e);
+            let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id)).await;
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!("Created and connected to device {} on bus {}", device_info.dev_id, device_info.bus_id);
+
+    stream.on_disconnect(|| {
+        eprintln!("Device disconnected by server");
+        std::process::exit(0);
+    }).expect("Failed to register disconnect callback");
+
+    // Shared with the `on_output` callback below, which runs on a reader
+    // task the client spawns internally - `record`/`record_output` both
+    // append to the same file, so they share one session.
+    let session: Arc<Mutex<Option<Recorder<KeyboardInput, LedState>>>> = Arc::new(Mutex::new(
+        match record_path {
+            Some(path) => {
+                println!("Recording to {}", path);
+                Some(Recorder::create(path, DEVICE_TYPE).await.expect("Failed to create recording file"))
+            }
+            None => None,
+        },
+    ));
+
+    let output_session = session.clone();
+    stream.on_output(move |stream| {
+        let output_session = output_session.clone();
+        async move {
+            use tokio::io::AsyncReadExt;
+            let mut buf = [0u8; OUTPUT_SIZE];
+            let mut guard = stream.lock().await;
+            guard.read_exact(&mut buf).await?;
+            drop(guard);
+            let leds = buf[0];
+            let state = LedState {
+                num_lock: (leds & 0x01) != 0,
+                caps_lock: (leds & 0x02) != 0,
+                scroll_lock: (leds & 0x04) != 0,
+                compose: (leds & 0x08) != 0,
+                kana: (leds & 0x10) != 0,
+            };
+            println!(
+                "← LEDs: Num={} Caps={} Scroll={} Compose={} Kana={}",
+                state.num_lock, state.caps_lock, state.scroll_lock, state.compose, state.kana
+            );
+            if let Some(session) = output_session.lock().await.as_mut() {
+                let _ = session.record_output(&state).await;
+            }
+            Ok(())
+        }
+    }).expect("Failed to register LED callback");
+
+    if let Some(path) = replay_path {
+        println!("Replaying {} at {}x speed. Press Ctrl+C to stop.", path, replay_speed);
+        if let Err(e) = recorder::replay::<KeyboardInput, LedState>(path, DEVICE_TYPE, replay_speed, &mut stream).await {
+            eprintln!("Replay error: {}", e);
+        }
+
+        let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
+        if created_bus {
+            let _ = client.bus_remove(Some(bus_id)).await;
+        }
+        return;
+    }
+
+    println!("Every 5s: type 'Hello!' + Enter. Press Ctrl+C to stop.");
+
+    // Queue up a whole phrase at a time instead of blocking the caller on
+    // sleep().await between key-down and key-up reports; a background task
+    // drains the queue at the correct cadence.
+    let queue = Arc::new(Mutex::new(ScheduleQueue::new()));
+    let stream = Arc::new(Mutex::new(stream));
+
+    {
+        let queue = queue.clone();
+        let stream = stream.clone();
+        let session = session.clone();
+        tokio::spawn(async move {
+            loop {
+                let ready = queue.lock().await.pump();
+                if !ready.is_empty() {
+                    let mut stream = stream.lock().await;
+                    for report in ready {
+                        if let Some(session) = session.lock().await.as_mut() {
+                            let _ = session.record(&report).await;
+                        }
+                        if let Err(e) = stream.send(&report).await {
+                            eprintln!("Write error: {}", e);
+                        }
+                    }
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        });
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+
+        let mut queue = queue.lock().await;
+        match type_string(&keymap, &mut queue, "Hello!") {
+            Ok(end) => {
+                tap(&mut queue, KEY_ENTER, 0, end + Duration::from_millis(100), 100, 100);
+                println!("→ Queued: Hello!");
+            }
+            Err(e) => eprintln!("Can't type phrase on this layout: {}", e),
+        }
+        drop(queue);
+    }
+
+    // Cleanup
+    let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
+    if created_bus {
+        let _ = client.bus_remove(Some(bus_id)).await;
+    }
+}
+
+/// Enqueue a key-down followed by a key-up `down_ms`/`up_ms` apart,
+/// `delay` from now. Returns the offset at which the tap finishes, so
+/// callers can chain further taps after it without blocking on sleep().
+fn tap(
+    queue: &mut ScheduleQueue<KeyboardInput>,
+    key: u8,
+    modifiers: u8,
+    delay: Duration,
+    down_ms: u64,
+    up_ms: u64,
+) -> Duration {
+    let down = KeyboardInput {
+        modifiers,
+        count: 1,
+        keys: vec![key],
+    };
+    let up = KeyboardInput {
+        modifiers: 0,
+        count: 0,
+        keys: vec![],
+    };
+    queue.schedule(down, delay);
+    queue.schedule(up, delay + Duration::from_millis(down_ms));
+    delay + Duration::from_millis(down_ms + up_ms)
+}
+
+/// Enqueue a whole phrase up front, driving `keymap` to turn each
+/// character into its chord sequence (possibly several key presses for
+/// dead-key / compose characters) instead of the old cast-to-`u8`
+/// `CHAR_TO_KEY` lookup. The background task drains it at the original
+/// 100ms-down/100ms-up cadence without blocking the caller. Returns the
+/// offset at which the last chord step finishes, or every character the
+/// active layout can't reproduce.
+fn type_string(
+    keymap: &Keymap,
+    queue: &mut ScheduleQueue<KeyboardInput>,
+    text: &str,
+) -> Result<Duration, keymap::UnmappableChars> {
+    let chords = keymap.encode(text)?;
+    let mut at = Duration::ZERO;
+    for step in chords {
+        at = tap(queue, step.key, step.modifiers, at, 100, 100);
+    }
+    Ok(at)
+}
@@ -0,0 +1,125 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::time::Instant;
+
+/// First line of every recording file: identifies which device type the
+/// reports below were captured from, using the same token passed in
+/// `DeviceCreateRequest` (e.g. `"keyboard"`, `"xbox360"`).
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    device_type: String,
+}
+
+/// One recorded entry: either a report sent to the device, or an output
+/// report (e.g. LEDs) the server sent back. Keeping both on the same
+/// timeline means a recording captures the full session, not just one
+/// direction of it.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "dir")]
+enum RecordedEntry<T, O> {
+    Sent { delta_ms: u64, report: T },
+    Output { delta_ms: u64, report: O },
+}
+
+/// Captures reports sent to, and output reports received from, a device
+/// stream to a newline-delimited JSON file, one [`RecordedEntry`] per
+/// line, so a session can be replayed later with `replay`.
+pub struct Recorder<T, O> {
+    start: Instant,
+    writer: BufWriter<File>,
+    _marker: std::marker::PhantomData<(T, O)>,
+}
+
+impl<T: Serialize, O: Serialize> Recorder<T, O> {
+    pub async fn create(path: &str, device_type: &str) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path).await?);
+        let header = RecordingHeader {
+            device_type: device_type.to_string(),
+        };
+        let mut line = serde_json::to_vec(&header)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        writer.flush().await?;
+        Ok(Self {
+            start: Instant::now(),
+            writer,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    pub async fn record(&mut self, report: &T) -> std::io::Result<()> {
+        let entry = RecordedEntry::Sent {
+            delta_ms: self.start.elapsed().as_millis() as u64,
+            report,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await?;
+        self.writer.flush().await
+    }
+
+    pub async fn record_output(&mut self, report: &O) -> std::io::Result<()> {
+        let entry = RecordedEntry::Output {
+            delta_ms: self.start.elapsed().as_millis() as u64,
+            report,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await?;
+        self.writer.flush().await
+    }
+}
+
+/// Reads back a recording created by [`Recorder`] and sends each sent
+/// report to `stream`, honoring the original inter-event timing (scaled
+/// by `speed`; `2.0` plays twice as fast). Output reports are skipped:
+/// they were produced by the server, not fed to it, so replaying them has
+/// nothing to send. Returns an error if the recording was captured from a
+/// different device type than `device_type`.
+pub async fn replay<T, O>(
+    path: &str,
+    device_type: &str,
+    speed: f64,
+    stream: &mut viiper_client::AsyncDeviceStream,
+) -> std::io::Result<()>
+where
+    T: Serialize + DeserializeOwned,
+    O: DeserializeOwned,
+{
+    let file = File::open(path).await?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header_line = lines.next_line().await?.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "empty recording")
+    })?;
+    let header: RecordingHeader = serde_json::from_str(&header_line)?;
+    if header.device_type != device_type {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "recording is for device type '{}', expected '{}'",
+                header.device_type, device_type
+            ),
+        ));
+    }
+
+    let mut last_delta = 0u64;
+    while let Some(line) = lines.next_line().await? {
+        let entry: RecordedEntry<T, O> = serde_json::from_str(&line)?;
+        let delta_ms = match &entry {
+            RecordedEntry::Sent { delta_ms, .. } => *delta_ms,
+            RecordedEntry::Output { delta_ms, .. } => *delta_ms,
+        };
+        let wait_ms = delta_ms.saturating_sub(last_delta) as f64 / speed;
+        tokio::time::sleep(Duration::from_millis(wait_ms.max(0.0) as u64)).await;
+        last_delta = delta_ms;
+        if let RecordedEntry::Sent { report, .. } = &entry {
+            if let Err(e) = stream.send(report).await {
+                eprintln!("Write error: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
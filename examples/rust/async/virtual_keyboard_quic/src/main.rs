@@ -0,0 +1,195 @@
+//! Same virtual keyboard as `virtual_keyboard`, but built against
+//! [`transport::Client`] instead of `viiper_client::AsyncViiperClient`
+//! directly, so it can pick QUIC (the default, via `quinn`/`rustls` since
+//! `viiper_client` only ever speaks TCP in this tree) or fall back to
+//! plain TCP with `--tcp` for backward compatibility - both transports
+//! answer to the same `bus_list`/`bus_create`/`bus_device_add`/
+//! `connect_device`/`send`/`on_disconnect`/`on_output` calls below.
+mod transport;
+
+use std::time::Duration;
+use tokio::time::sleep;
+use viiper_client::devices::keyboard::*;
+
+use transport::{Client, ClientConfig, Transport};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: {} <api_addr> [--tcp | --skip-verify | --ca <file>]",
+            args[0]
+        );
+        eprintln!("Example: {} localhost:3242 --skip-verify", args[0]);
+        std::process::exit(1);
+    }
+
+    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
+        eprintln!("Invalid address '{}': {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    let transport = if args.iter().any(|a| a == "--tcp") {
+        Transport::Tcp
+    } else {
+        let mut builder = ClientConfig::builder();
+        match args.get(2).map(String::as_str) {
+            Some("--skip-verify") => {
+                builder = builder.skip_verify(true);
+            }
+            Some("--ca") => {
+                let ca_path = args.get(3).unwrap_or_else(|| {
+                    eprintln!("--ca requires a file path");
+                    std::process::exit(1);
+                });
+                builder = builder.trust_ca_file(ca_path).unwrap_or_else(|e| {
+                    eprintln!("Failed to load CA file: {}", e);
+                    std::process::exit(1);
+                });
+            }
+            _ => {}
+        }
+        let config = builder.build().unwrap_or_else(|e| {
+            eprintln!("Invalid client config: {}", e);
+            std::process::exit(1);
+        });
+        Transport::Quic(config)
+    };
+
+    // Every device stream this client opens after this point multiplexes
+    // over the same connection (one QUIC stream per `connect_device`, or
+    // the same TCP socket `viiper_client` already manages) instead of a
+    // fresh socket per call.
+    let client = match Client::connect(addr, transport).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Connect failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let (bus_id, created_bus) = match client.bus_list().await {
+        Ok(resp) if resp.buses.is_empty() => match client.bus_create(None).await {
+            Ok(r) => {
+                println!("Created bus {}", r.bus_id);
+                (r.bus_id, true)
+            }
+            Err(e) => {
+                eprintln!("BusCreate failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Ok(resp) => {
+            let bus_id = *resp.buses.iter().min().unwrap();
+            println!("Using existing bus {}", bus_id);
+            (bus_id, false)
+        }
+        Err(e) => {
+            eprintln!("BusList error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let device_info = match client
+        .bus_device_add(
+            bus_id,
+            &viiper_client::types::DeviceCreateRequest {
+                r#type: Some("keyboard".to_string()),
+                id_vendor: None,
+                id_product: None,
+            },
+        )
+        .await
+    {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("AddDevice error: {}", e);
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id)).await;
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let mut stream = match client
+        .connect_device(device_info.bus_id, &device_info.dev_id)
+        .await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ConnectDevice error: {}", e);
+            let _ = client
+                .bus_device_remove(device_info.bus_id, Some(&device_info.dev_id))
+                .await;
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id)).await;
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Created and connected to device {} on bus {}",
+        device_info.dev_id, device_info.bus_id
+    );
+
+    stream
+        .on_disconnect(|| {
+            eprintln!("Device disconnected by server");
+            std::process::exit(0);
+        })
+        .expect("Failed to register disconnect callback");
+
+    stream
+        .on_output(
+            OUTPUT_SIZE,
+            |buf| {
+                let leds = buf[0];
+                (
+                    (leds & 0x01) != 0,
+                    (leds & 0x02) != 0,
+                    (leds & 0x04) != 0,
+                    (leds & 0x08) != 0,
+                    (leds & 0x10) != 0,
+                )
+            },
+            |(num_lock, caps_lock, scroll_lock, compose, kana)| {
+                println!(
+                    "← LEDs: Num={} Caps={} Scroll={} Compose={} Kana={}",
+                    num_lock, caps_lock, scroll_lock, compose, kana
+                );
+            },
+        )
+        .expect("Failed to register LED callback");
+
+    loop {
+        let press = KeyboardInput {
+            modifiers: 0,
+            count: 1,
+            keys: vec![KEY_ENTER],
+        };
+        if let Err(e) = stream.send(&press).await {
+            eprintln!("Write error: {}", e);
+            break;
+        }
+        let release = KeyboardInput {
+            modifiers: 0,
+            count: 0,
+            keys: vec![],
+        };
+        if let Err(e) = stream.send(&release).await {
+            eprintln!("Write error: {}", e);
+            break;
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+
+    // Cleanup
+    let _ = client
+        .bus_device_remove(device_info.bus_id, Some(&device_info.dev_id))
+        .await;
+    if created_bus {
+        let _ = client.bus_remove(Some(bus_id)).await;
+    }
+}
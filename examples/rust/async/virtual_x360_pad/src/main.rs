@@ -1,20 +1,56 @@
+mod recorder;
+mod shm_batch;
+mod shm_ring;
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use tokio::time::Duration;
 use viiper_client::{AsyncViiperClient, devices::xbox360::*};
 
+use recorder::Recorder;
+use shm_batch::BatchedStream;
+
+const DEVICE_TYPE: &str = "xbox360";
+
+/// Decoded form of the rumble output report, recorded alongside the
+/// inputs sent to the device, instead of only ever being printed.
+#[derive(Serialize, Deserialize)]
+struct RumbleState {
+    left: u8,
+    right: u8,
+}
+
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: {} <api_addr>", args[0]);
-        eprintln!("Example: {} localhost:3242", args[0]);
+        eprintln!("Usage: {} <api_addr> [--record <file> | --replay <file> [speed]]", args[0]);
+        eprintln!("Example: {} localhost:3242 --record session.viiper", args[0]);
         std::process::exit(1);
     }
 
+    let record_path = match args.get(2).map(String::as_str) {
+        Some("--record") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--record requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_path = match args.get(2).map(String::as_str) {
+        Some("--replay") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--replay requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_speed: f64 = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(1.0);
+
     let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
         eprintln!("Invalid address '{}': {}", args[1], e);
         std::process::exit(1);
     });
-    
+
     let client = AsyncViiperClient::new(addr);
 
     // Find or create a bus
@@ -78,33 +114,76 @@ async fn main() {
         std::process::exit(0);
     }).expect("Failed to register disconnect callback");
 
-    stream.on_output(|stream| async move {
-        use tokio::io::AsyncReadExt;
-        let mut buf = [0u8; OUTPUT_SIZE];
-        let mut guard = stream.lock().await;
-        guard.read_exact(&mut buf).await?;
-        drop(guard);
-        let left = buf[0];
-        let right = buf[1];
-        println!("← Rumble: Left={}, Right={}", left, right);
-        Ok(())
+    // Shared with the send loop below, which records `Sent` entries to
+    // the same file as the `Output` entries the rumble callback records.
+    let session: Arc<Mutex<Option<Recorder<Xbox360Input, RumbleState>>>> = Arc::new(Mutex::new(
+        match record_path {
+            Some(path) => {
+                println!("Recording to {}", path);
+                Some(Recorder::create(path, DEVICE_TYPE).await.expect("Failed to create recording file"))
+            }
+            None => None,
+        },
+    ));
+
+    let output_session = session.clone();
+    stream.on_output(move |stream| {
+        let output_session = output_session.clone();
+        async move {
+            use tokio::io::AsyncReadExt;
+            let mut buf = [0u8; OUTPUT_SIZE];
+            let mut guard = stream.lock().await;
+            guard.read_exact(&mut buf).await?;
+            drop(guard);
+            let state = RumbleState { left: buf[0], right: buf[1] };
+            println!("← Rumble: Left={}, Right={}", state.left, state.right);
+            if let Some(session) = output_session.lock().await.as_mut() {
+                let _ = session.record_output(&state).await;
+            }
+            Ok(())
+        }
     }).expect("Failed to register rumble callback");
 
-    // Send controller inputs at 60fps (16ms intervals)
+    if let Some(path) = replay_path {
+        println!("Replaying {} at {}x speed. Press Ctrl+C to stop.", path, replay_speed);
+        if let Err(e) = recorder::replay::<Xbox360Input, RumbleState>(path, DEVICE_TYPE, replay_speed, &mut stream).await {
+            eprintln!("Replay error: {}", e);
+        }
+
+        let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id)).await;
+        if created_bus {
+            let _ = client.bus_remove(Some(bus_id)).await;
+        }
+        return;
+    }
+
+    // Send controller inputs at 60fps (16ms intervals). Frames are
+    // batched into a ring buffer and a consumer task drains it, sending
+    // each report over the stream; the ring is in this process only, so
+    // every report is still its own network write. `shm_available` stands in for
+    // the real negotiation with the server; `BatchedStream` falls back to
+    // the stream's normal per-message path internally when it's false, so
+    // the send loop below always calls `send_batch`/`flush` unconditionally.
+    let shm_available = true;
+    let stream = Arc::new(Mutex::new(stream));
+    let (mut batched, _consumer) = BatchedStream::new(shm_available, stream.clone());
+
+    const BATCH_SIZE: usize = 4;
     let mut frame = 0u64;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
     let mut interval = tokio::time::interval(Duration::from_millis(16));
-    
+
     loop {
         interval.tick().await;
         frame += 1;
-        
+
         let buttons = match (frame / 60) % 4 {
             0 => BUTTON_A,
             1 => BUTTON_B,
             2 => BUTTON_X,
             _ => BUTTON_Y,
         };
-        
+
         let state = Xbox360Input {
             buttons: buttons as u32,
             lt: ((frame * 2) % 256) as u8,
@@ -114,16 +193,28 @@ async fn main() {
             rx: 0,
             ry: 0,
         };
-        
-        if let Err(e) = stream.send(&state).await {
-            eprintln!("Write error: {}", e);
-            break;
-        }
-        
+
         if frame % 60 == 0 {
-            println!("→ Sent input (frame {}): buttons=0x{:04x}, LT={}, RT={}", 
+            println!("→ Sent input (frame {}): buttons=0x{:04x}, LT={}, RT={}",
                 frame, state.buttons, state.lt, state.rt);
         }
+
+        if let Some(session) = session.lock().await.as_mut() {
+            let _ = session.record(&state).await;
+        }
+
+        batch.push(state);
+        if batch.len() == BATCH_SIZE {
+            if let Err(e) = batched.send_batch(&batch).await {
+                eprintln!("Write error: {}", e);
+                break;
+            }
+            if let Err(e) = batched.flush().await {
+                eprintln!("Flush error: {}", e);
+                break;
+            }
+            batch.clear();
+        }
     }
 
     // Cleanup
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use viiper_client::devices::xbox360::*;
+
+use crate::shm_ring::ShmRing;
+
+/// Wire size of one `Xbox360Input`: `buttons: u32` + `lt: u8` + `rt: u8`
+/// + `lx/ly/rx/ry: i16` = 4 + 1 + 1 + 2*4 = 14 bytes.
+const REPORT_SIZE: usize = 14;
+const RING_CAPACITY: usize = 64;
+
+fn encode(report: &Xbox360Input) -> [u8; REPORT_SIZE] {
+    let mut buf = [0u8; REPORT_SIZE];
+    buf[0..4].copy_from_slice(&report.buttons.to_le_bytes());
+    buf[4] = report.lt;
+    buf[5] = report.rt;
+    buf[6..8].copy_from_slice(&report.lx.to_le_bytes());
+    buf[8..10].copy_from_slice(&report.ly.to_le_bytes());
+    buf[10..12].copy_from_slice(&report.rx.to_le_bytes());
+    buf[12..14].copy_from_slice(&report.ry.to_le_bytes());
+    buf
+}
+
+fn decode(buf: &[u8; REPORT_SIZE]) -> Xbox360Input {
+    Xbox360Input {
+        buttons: u32::from_le_bytes(buf[0..4].try_into().unwrap()),
+        lt: buf[4],
+        rt: buf[5],
+        lx: i16::from_le_bytes(buf[6..8].try_into().unwrap()),
+        ly: i16::from_le_bytes(buf[8..10].try_into().unwrap()),
+        rx: i16::from_le_bytes(buf[10..12].try_into().unwrap()),
+        ry: i16::from_le_bytes(buf[12..14].try_into().unwrap()),
+    }
+}
+
+/// Batched send: reports are encoded into a [`ShmRing`] and a consumer
+/// task drains it on each one-byte wakeup posted by `flush`, sending
+/// every drained report over `stream` with `send`. The ring lives in
+/// this process's memory and is not shared with the server, so each
+/// report is still one `send` on the stream. Falls back transparently to the stream's normal per-message
+/// path when the server didn't negotiate the shm handshake - callers
+/// always go through `send_batch`/`flush` and never need to check
+/// `shm_available` themselves.
+pub struct BatchedStream {
+    ring: Arc<ShmRing<REPORT_SIZE>>,
+    wakeup: UnixStream,
+    // Bumped by the consumer task every time it finishes draining the
+    // ring, so `send_batch` can wait for a drain that finishes *after* it
+    // found the ring full, instead of just posting a wakeup byte and
+    // hoping the consumer got to it in time.
+    drained: mpsc::Receiver<()>,
+    shm_available: bool,
+    stream: Arc<Mutex<viiper_client::AsyncDeviceStream>>,
+}
+
+impl BatchedStream {
+    /// `shm_available` stands in for the real negotiation with the
+    /// server (a capability flag in the `connect_device` handshake).
+    pub fn new(
+        shm_available: bool,
+        stream: Arc<Mutex<viiper_client::AsyncDeviceStream>>,
+    ) -> (Self, JoinHandle<()>) {
+        let ring = Arc::new(ShmRing::new(RING_CAPACITY));
+        let (wakeup, mut consumer_wakeup) =
+            UnixStream::pair().expect("failed to create wakeup socketpair");
+        // Capacity 1: only the most recent drain completion matters to
+        // `send_batch` - if several drains finish before a waiting
+        // `send_batch` wakes up, it only needs to know that at least one
+        // ran. It discards any older completion before it waits.
+        let (drained_tx, drained_rx) = mpsc::channel(1);
+
+        let consumer_ring = ring.clone();
+        let consumer_stream = stream.clone();
+        let handle = tokio::spawn(async move {
+            let mut signal = [0u8; 1];
+            let mut batch = Vec::new();
+            // One byte in the socketpair means "the producer advanced
+            // the ring at least once since you last drained it" - never
+            // more than one pending wakeup is needed since `drain` always
+            // empties the ring.
+            while consumer_wakeup.read_exact(&mut signal).await.is_ok() {
+                batch.clear();
+                consumer_ring.drain(&mut batch);
+                {
+                    let mut stream = consumer_stream.lock().await;
+                    for bytes in &batch {
+                        if let Err(e) = stream.send(&decode(bytes)).await {
+                            eprintln!("Write error: {}", e);
+                        }
+                    }
+                }
+                // Best-effort: if nobody's waiting on `flush` right now
+                // this drops silently, which is fine - it only exists to
+                // unblock a `flush` call, not to record history.
+                let _ = drained_tx.try_send(());
+            }
+        });
+
+        (
+            Self {
+                ring,
+                wakeup,
+                drained: drained_rx,
+                shm_available,
+                stream,
+            },
+            handle,
+        )
+    }
+
+    /// Queue every report in `reports`. When shm is available they go
+    /// onto the ring and `flush` wakes the consumer; otherwise each
+    /// report is sent over `stream` directly, exactly as before this
+    /// fast path existed. If the ring fills up because the consumer has
+    /// fallen behind, this waits for a drain to complete and retries
+    /// before giving up - so a report is only ever dropped with an error
+    /// telling the caller so, never silently.
+    pub async fn send_batch(&mut self, reports: &[Xbox360Input]) -> std::io::Result<()> {
+        if !self.shm_available {
+            let mut stream = self.stream.lock().await;
+            for report in reports {
+                stream.send(report).await?;
+            }
+            return Ok(());
+        }
+
+        for report in reports {
+            let bytes = encode(report);
+            if !self.ring.push(&bytes) {
+                // Discard a completion left over from an earlier drain, so
+                // the wait below only returns for one that ends after this.
+                while self.drained.try_recv().is_ok() {}
+                self.flush().await?;
+                self.wait_for_drain().await;
+                if !self.ring.push(&bytes) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::WouldBlock,
+                        "shm ring still full after a drain; report dropped",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Post the one-byte wakeup that tells the consumer task to drain the
+    /// ring. Safe to call even if nothing new was pushed, and a no-op
+    /// when shm isn't available (every report already went straight over
+    /// the stream in `send_batch`).
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        if !self.shm_available {
+            return Ok(());
+        }
+        self.wakeup.write_all(&[1u8]).await
+    }
+
+    /// Blocks until the consumer task finishes a drain, so a caller that
+    /// just posted a wakeup can be sure the ring has room before retrying
+    /// a push, instead of racing the consumer.
+    async fn wait_for_drain(&mut self) {
+        let _ = self.drained.recv().await;
+    }
+}
@@ -0,0 +1,81 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Fixed-size single-producer/single-consumer ring buffer of already
+/// encoded, fixed-size input reports (report size is known per device,
+/// the same way `OUTPUT_SIZE` is known on the output side).
+///
+/// # Memory ordering
+///
+/// The producer ([`push`]) writes a report's bytes into
+/// `slots[tail % capacity]` and only *then* stores the advanced `tail`
+/// index with [`Ordering::Release`]. The consumer ([`drain`]) loads
+/// `tail` with [`Ordering::Acquire`]; that Acquire/Release pair
+/// guarantees the consumer can never observe a new `tail` value without
+/// also observing the fully-written slot bytes that precede it - no torn
+/// reads of a half-written report.
+///
+/// Symmetrically, the consumer stores the advanced `head` index with
+/// [`Ordering::Release`] after it has copied a slot out, and the producer
+/// loads `head` with [`Ordering::Acquire`] before reusing that slot. This
+/// guarantees the producer never overwrites a slot the consumer hasn't
+/// finished reading yet.
+///
+/// [`push`]: ShmRing::push
+/// [`drain`]: ShmRing::drain
+pub struct ShmRing<const N: usize> {
+    slots: Box<[UnsafeCell<[u8; N]>]>,
+    capacity: usize,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// Safety: `push` is only ever called by the single producer and `drain`
+// only by the single consumer; the Acquire/Release pairs on `head`/`tail`
+// above ensure the two never touch the same slot at the same time.
+unsafe impl<const N: usize> Sync for ShmRing<N> {}
+
+impl<const N: usize> ShmRing<N> {
+    pub fn new(capacity: usize) -> Self {
+        let slots = (0..capacity)
+            .map(|_| UnsafeCell::new([0u8; N]))
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+        Self {
+            slots,
+            capacity,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Producer-only. Returns `false` (report not queued) if the ring is
+    /// full, i.e. the producer has lapped the consumer.
+    pub fn push(&self, report: &[u8; N]) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.capacity {
+            return false;
+        }
+        // Safety: this slot is not in [head, tail) from the consumer's
+        // point of view, so only the producer touches it right now.
+        let slot = unsafe { &mut *self.slots[tail % self.capacity].get() };
+        slot.copy_from_slice(report);
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Consumer-only. Appends every ready report, in FIFO order, to `out`.
+    pub fn drain(&self, out: &mut Vec<[u8; N]>) {
+        let tail = self.tail.load(Ordering::Acquire);
+        let mut head = self.head.load(Ordering::Relaxed);
+        while head != tail {
+            // Safety: the producer won't reuse this slot until it
+            // observes the `head` store below.
+            let slot = unsafe { &*self.slots[head % self.capacity].get() };
+            out.push(*slot);
+            head = head.wrapping_add(1);
+        }
+        self.head.store(head, Ordering::Release);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// USB HID keyboard/keypad usage IDs for the keys these layouts reference.
+// `KeyboardInput::keys` is a plain `Vec<u8>` of these, so the engine can
+// build chords without needing every named constant the library exposes.
+const KEY_A: u8 = 4;
+const KEY_E: u8 = 8;
+const KEY_N: u8 = 17;
+const KEY_U: u8 = 24;
+const KEY_Y: u8 = 28;
+const KEY_Z: u8 = 29;
+const KEY_1: u8 = 30;
+const KEY_0: u8 = 39;
+const KEY_SPACE: u8 = 44;
+const KEY_MINUS: u8 = 45;
+const KEY_LEFT_BRACE: u8 = 47;
+const KEY_SEMICOLON: u8 = 51;
+const KEY_APOSTROPHE: u8 = 52;
+const KEY_GRAVE: u8 = 53;
+const KEY_COMMA: u8 = 54;
+const KEY_PERIOD: u8 = 55;
+const KEY_SLASH: u8 = 56;
+
+const MOD_LEFT_SHIFT: u8 = 0x02;
+
+/// One physical key press (with held modifiers) needed to reproduce part
+/// of a character. Most characters are a single step; dead-key/compose
+/// sequences need several, e.g. "acute + e" for "é".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChordStep {
+    pub modifiers: u8,
+    pub key: u8,
+}
+
+impl ChordStep {
+    const fn new(modifiers: u8, key: u8) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+/// Unicode scalars that could not be reproduced on the active layout.
+/// Returned instead of silently dropping them.
+#[derive(Debug)]
+pub struct UnmappableChars {
+    pub chars: Vec<char>,
+}
+
+impl fmt::Display for UnmappableChars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unmappable characters on this layout: {:?}", self.chars)
+    }
+}
+
+impl std::error::Error for UnmappableChars {}
+
+/// A selectable layout mapping full Unicode scalars to the chord sequence
+/// that types them, so `type_string` can drive arbitrary text instead of
+/// truncating each `char` to a `u8` and hoping it lands in `CHAR_TO_KEY`.
+pub struct Keymap {
+    chords: HashMap<char, Vec<ChordStep>>,
+}
+
+impl Keymap {
+    fn from_ascii_table(entries: &[(char, u8, bool)]) -> HashMap<char, Vec<ChordStep>> {
+        entries
+            .iter()
+            .map(|&(ch, key, shifted)| {
+                let modifiers = if shifted { MOD_LEFT_SHIFT } else { 0 };
+                (ch, vec![ChordStep::new(modifiers, key)])
+            })
+            .collect()
+    }
+
+    /// The standard US QWERTY layout: one chord step per character, no
+    /// dead keys. Covers letters, digits, space and `!,./-'` plus the
+    /// backtick; other ASCII symbols are not mapped.
+    pub fn us_qwerty() -> Self {
+        let mut entries = vec![
+            (' ', KEY_SPACE, false),
+            ('!', KEY_1, true),
+            (',', KEY_COMMA, false),
+            ('.', KEY_PERIOD, false),
+            ('/', KEY_SLASH, false),
+            ('-', KEY_MINUS, false),
+            ('\'', KEY_APOSTROPHE, false),
+            ('`', KEY_GRAVE, false),
+        ];
+        for (i, letter) in ('a'..='z').enumerate() {
+            let key = KEY_A + i as u8;
+            entries.push((letter, key, false));
+            entries.push((letter.to_ascii_uppercase(), key, true));
+        }
+        for digit in 0..=9u8 {
+            let key = if digit == 0 { KEY_0 } else { KEY_1 + digit - 1 };
+            entries.push(((b'0' + digit) as char, key, false));
+        }
+        Self {
+            chords: Self::from_ascii_table(&entries),
+        }
+    }
+
+    /// US International: the same direct mappings as `us_qwerty`, plus
+    /// dead-key compose sequences for accented Latin-1 characters that
+    /// have no dedicated key (acute "'", grave "`", diaeresis and tilde
+    /// dead keys followed by the base letter).
+    pub fn us_international() -> Self {
+        let mut layout = Self::us_qwerty();
+        let dead_acute = ChordStep::new(0, KEY_APOSTROPHE);
+        let dead_grave = ChordStep::new(0, KEY_GRAVE);
+        let dead_diaeresis = ChordStep::new(MOD_LEFT_SHIFT, KEY_APOSTROPHE);
+        let dead_tilde = ChordStep::new(MOD_LEFT_SHIFT, KEY_GRAVE);
+
+        layout.chords.insert('é', vec![dead_acute, ChordStep::new(0, KEY_E)]);
+        layout.chords.insert('è', vec![dead_grave, ChordStep::new(0, KEY_E)]);
+        layout.chords.insert('ü', vec![dead_diaeresis, ChordStep::new(0, KEY_U)]);
+        layout.chords.insert('ñ', vec![dead_tilde, ChordStep::new(0, KEY_N)]);
+        layout
+    }
+
+    /// A non-US layout (German QWERTZ): Y and Z are swapped relative to
+    /// QWERTY, and "ü"/"ö" are typed on their own keys (the US "[" and
+    /// ";" positions). Every other character keeps its `us_qwerty` key.
+    pub fn de_qwertz() -> Self {
+        let mut layout = Self::us_qwerty();
+        layout.chords.insert('y', vec![ChordStep::new(0, KEY_Z)]);
+        layout.chords.insert('Y', vec![ChordStep::new(MOD_LEFT_SHIFT, KEY_Z)]);
+        layout.chords.insert('z', vec![ChordStep::new(0, KEY_Y)]);
+        layout.chords.insert('Z', vec![ChordStep::new(MOD_LEFT_SHIFT, KEY_Y)]);
+        layout.chords.insert('ü', vec![ChordStep::new(0, KEY_LEFT_BRACE)]);
+        layout.chords.insert('ö', vec![ChordStep::new(0, KEY_SEMICOLON)]);
+        layout
+    }
+
+    /// Encode `text` into the chord sequence that reproduces it on this
+    /// layout. Returns every unmappable character instead of dropping
+    /// them, so callers can decide how to handle a partial phrase.
+    pub fn encode(&self, text: &str) -> Result<Vec<ChordStep>, UnmappableChars> {
+        let mut steps = Vec::new();
+        let mut unmapped = Vec::new();
+        for ch in text.chars() {
+            match self.chords.get(&ch) {
+                Some(chord) => steps.extend_from_slice(chord),
+                None => unmapped.push(ch),
+            }
+        }
+        if unmapped.is_empty() {
+            Ok(steps)
+        } else {
+            Err(UnmappableChars { chars: unmapped })
+        }
+    }
+}
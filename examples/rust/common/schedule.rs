@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A single input report queued for delivery once `wait_time` has elapsed
+/// since it was scheduled.
+pub struct ScheduledInput<T> {
+    report: T,
+    scheduled_time: Instant,
+    wait_time: Duration,
+}
+
+impl<T> ScheduledInput<T> {
+    pub fn new(report: T, wait_time: Duration) -> Self {
+        Self {
+            report,
+            scheduled_time: Instant::now(),
+            wait_time,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.scheduled_time.elapsed() > self.wait_time
+    }
+
+    fn ready_at(&self) -> Instant {
+        self.scheduled_time + self.wait_time
+    }
+}
+
+/// Queue of pending reports kept ordered by ready time, so callers can
+/// enqueue a whole sequence up front and let it drain at the right cadence
+/// instead of blocking the caller between each report. Shared as-is
+/// between the sync and async `virtual_keyboard` examples: the queue
+/// itself never blocks or awaits, so the same type serves both a
+/// `thread::sleep` poll loop and a `tokio::time::sleep` one.
+pub struct ScheduleQueue<T> {
+    pending: VecDeque<ScheduledInput<T>>,
+}
+
+impl<T> ScheduleQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Enqueue `report` to be sent after `delay`. Insertion keeps the queue
+    /// ordered by ready time while preserving FIFO order among reports that
+    /// share the same ready time.
+    pub fn schedule(&mut self, report: T, delay: Duration) {
+        let input = ScheduledInput::new(report, delay);
+        let ready_at = input.ready_at();
+        let pos = self
+            .pending
+            .iter()
+            .position(|queued| queued.ready_at() > ready_at)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(pos, input);
+    }
+
+    /// Pop and return every report that has become ready, in FIFO order.
+    /// Reports still waiting are left untouched in the queue.
+    pub fn pump(&mut self) -> Vec<T> {
+        let mut ready = Vec::new();
+        while let Some(front) = self.pending.front() {
+            if !front.is_ready() {
+                break;
+            }
+            ready.push(self.pending.pop_front().unwrap().report);
+        }
+        ready
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
@@ -0,0 +1,224 @@
+//! Forwards a real Linux gamepad into a virtual xbox360 device, so VIIPER
+//! can re-route an existing controller instead of only synthesizing input.
+use evdev::{AbsoluteAxisType, Device, InputEventKind, Key};
+use std::collections::HashMap;
+use std::time::Duration;
+use viiper_client::{devices::xbox360::*, ViiperClient};
+
+/// Host evdev button code -> virtual xbox360 button bit.
+fn default_button_map() -> HashMap<Key, u32> {
+    HashMap::from([
+        (Key::BTN_SOUTH, BUTTON_A),
+        (Key::BTN_EAST, BUTTON_B),
+        (Key::BTN_WEST, BUTTON_X),
+        (Key::BTN_NORTH, BUTTON_Y),
+        (Key::BTN_TL, BUTTON_LB),
+        (Key::BTN_TR, BUTTON_RB),
+        (Key::BTN_START, BUTTON_START),
+        (Key::BTN_SELECT, BUTTON_BACK),
+        (Key::BTN_THUMBL, BUTTON_LS),
+        (Key::BTN_THUMBR, BUTTON_RS),
+    ])
+}
+
+/// Rescale a host axis range (read from the device's own `AbsInfo`, not
+/// assumed) onto the `i16` stick range VIIPER expects, clamping anything
+/// inside `deadzone` (as a fraction of full scale) to zero.
+fn scale_axis(value: i32, min: i32, max: i32, deadzone: f32) -> i16 {
+    let mid = (min + max) as f32 / 2.0;
+    let half_range = (max - min) as f32 / 2.0;
+    let normalized = (value as f32 - mid) / half_range; // -1.0..=1.0
+    if normalized.abs() < deadzone {
+        return 0;
+    }
+    (normalized.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// Rescale a host trigger range (read from the device's own `AbsInfo`)
+/// onto the `u8` trigger range VIIPER expects.
+fn scale_trigger(value: i32, min: i32, max: i32) -> u8 {
+    let normalized = (value - min) as f32 / (max - min) as f32;
+    (normalized.clamp(0.0, 1.0) * u8::MAX as f32) as u8
+}
+
+/// Look up the host's reported `(minimum, maximum)` for `axis`, falling
+/// back to the most common reported range for that axis type if the
+/// device didn't advertise one (some evdev drivers omit `AbsInfo` for
+/// axes they still emit events on).
+fn axis_range(source: &Device, axis: AbsoluteAxisType, fallback: (i32, i32)) -> (i32, i32) {
+    source
+        .get_abs_state()
+        .ok()
+        .and_then(|state| state.get(axis.0 as usize).copied())
+        .map(|info| (info.minimum, info.maximum))
+        .filter(|&(min, max)| min < max)
+        .unwrap_or(fallback)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <api_addr> <evdev_path>", args[0]);
+        eprintln!("Example: {} localhost:3242 /dev/input/event5", args[0]);
+        std::process::exit(1);
+    }
+
+    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
+        eprintln!("Invalid address '{}': {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    let mut source = Device::open(&args[2]).unwrap_or_else(|e| {
+        eprintln!("Failed to open '{}': {}", args[2], e);
+        std::process::exit(1);
+    });
+    let button_map = default_button_map();
+    let deadzone = 0.1;
+
+    let client = ViiperClient::new(addr);
+
+    let (bus_id, created_bus) = match client.bus_list() {
+        Ok(resp) if resp.buses.is_empty() => match client.bus_create(None) {
+            Ok(r) => (r.bus_id, true),
+            Err(e) => {
+                eprintln!("BusCreate failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Ok(resp) => (*resp.buses.iter().min().unwrap(), false),
+        Err(e) => {
+            eprintln!("BusList error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let device_info = match client.bus_device_add(
+        bus_id,
+        &viiper_client::types::DeviceCreateRequest {
+            r#type: Some("xbox360".to_string()),
+            id_vendor: None,
+            id_product: None,
+        },
+    ) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("AddDevice error: {}", e);
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let mut stream = match client.connect_device(device_info.bus_id, &device_info.dev_id) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ConnectDevice error: {}", e);
+            let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id));
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Forwarding {} into virtual xbox360 device {} on bus {}. Press Ctrl+C to stop.",
+        args[2], device_info.dev_id, device_info.bus_id
+    );
+
+    let mut state = Xbox360Input {
+        buttons: 0,
+        lt: 0,
+        rt: 0,
+        lx: 0,
+        ly: 0,
+        rx: 0,
+        ry: 0,
+    };
+
+    loop {
+        let events = match source.fetch_events() {
+            Ok(events) => events.collect::<Vec<_>>(),
+            Err(_) => {
+                // Source device disappeared (unplugged) - release every
+                // held button/axis before shutting down cleanly.
+                eprintln!("Source device disconnected, releasing held input");
+                let _ = stream.send(&Xbox360Input {
+                    buttons: 0,
+                    lt: 0,
+                    rt: 0,
+                    lx: 0,
+                    ly: 0,
+                    rx: 0,
+                    ry: 0,
+                });
+                break;
+            }
+        };
+
+        let mut changed = false;
+        for event in events {
+            match event.kind() {
+                InputEventKind::Key(key) => {
+                    if let Some(&bit) = button_map.get(&key) {
+                        if event.value() != 0 {
+                            state.buttons |= bit;
+                        } else {
+                            state.buttons &= !bit;
+                        }
+                        changed = true;
+                    }
+                }
+                InputEventKind::AbsAxis(axis) => match axis {
+                    AbsoluteAxisType::ABS_X => {
+                        let (min, max) = axis_range(&source, axis, (-32768, 32767));
+                        state.lx = scale_axis(event.value(), min, max, deadzone);
+                        changed = true;
+                    }
+                    AbsoluteAxisType::ABS_Y => {
+                        let (min, max) = axis_range(&source, axis, (-32768, 32767));
+                        state.ly = scale_axis(event.value(), min, max, deadzone);
+                        changed = true;
+                    }
+                    AbsoluteAxisType::ABS_RX => {
+                        let (min, max) = axis_range(&source, axis, (-32768, 32767));
+                        state.rx = scale_axis(event.value(), min, max, deadzone);
+                        changed = true;
+                    }
+                    AbsoluteAxisType::ABS_RY => {
+                        let (min, max) = axis_range(&source, axis, (-32768, 32767));
+                        state.ry = scale_axis(event.value(), min, max, deadzone);
+                        changed = true;
+                    }
+                    AbsoluteAxisType::ABS_Z => {
+                        let (min, max) = axis_range(&source, axis, (0, 255));
+                        state.lt = scale_trigger(event.value(), min, max);
+                        changed = true;
+                    }
+                    AbsoluteAxisType::ABS_RZ => {
+                        let (min, max) = axis_range(&source, axis, (0, 255));
+                        state.rt = scale_trigger(event.value(), min, max);
+                        changed = true;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        if changed {
+            if let Err(e) = stream.send(&state) {
+                eprintln!("Write error: {}", e);
+                break;
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id));
+    if created_bus {
+        let _ = client.bus_remove(Some(bus_id));
+    }
+}
@@ -1,15 +1,70 @@
+mod keymap;
+mod recorder;
+mod schedule;
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use viiper_client::{devices::keyboard::*, ViiperClient};
 
+use keymap::Keymap;
+use recorder::Recorder;
+use schedule::ScheduleQueue;
+
+const DEVICE_TYPE: &str = "keyboard";
+
+/// Decoded form of the LED output report, recorded alongside the keys
+/// sent to the device, instead of only ever being printed. Replay skips
+/// these entries.
+#[derive(Serialize, Deserialize)]
+struct LedState {
+    num_lock: bool,
+    caps_lock: bool,
+    scroll_lock: bool,
+    compose: bool,
+    kana: bool,
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: {} <api_addr>", args[0]);
-        eprintln!("Example: {} localhost:3242", args[0]);
+        eprintln!("Usage: {} <api_addr> [--record <file> | --replay <file> [speed]] [--layout us|us-intl|de]", args[0]);
+        eprintln!("Example: {} localhost:3242 --record session.viiper", args[0]);
         std::process::exit(1);
     }
 
+    let record_path = match args.get(2).map(String::as_str) {
+        Some("--record") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--record requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_path = match args.get(2).map(String::as_str) {
+        Some("--replay") => Some(args.get(3).unwrap_or_else(|| {
+            eprintln!("--replay requires a file path");
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let replay_speed: f64 = args.get(4).and_then(|s| s.parse().ok()).unwrap_or(1.0);
+
+    let keymap = match args
+        .iter()
+        .position(|a| a == "--layout")
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+    {
+        Some("us-intl") => Keymap::us_international(),
+        Some("de") => Keymap::de_qwertz(),
+        Some("us") | None => Keymap::us_qwerty(),
+        Some(other) => {
+            eprintln!("Unknown layout '{}', expected one of: us, us-intl, de", other);
+            std::process::exit(1);
+        }
+    };
+
     let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
         eprintln!("Invalid address '{}': {}", args[1], e);
         std::process::exit(1);
@@ -84,42 +139,79 @@ fn main() {
         })
         .expect("Failed to register disconnect callback");
 
+    // Shared with the `on_output` callback below, which may run on a
+    // reader thread the client spawns internally - `record`/`record_output`
+    // both append to the same file, so they share one session.
+    let session: Arc<Mutex<Option<Recorder<KeyboardInput, LedState>>>> = Arc::new(Mutex::new(
+        record_path.map(|path| {
+            println!("Recording to {}", path);
+            Recorder::create(path, DEVICE_TYPE).expect("Failed to create recording file")
+        }),
+    ));
+
+    let output_session = session.clone();
     stream
-        .on_output(|reader| {
+        .on_output(move |reader| {
             let mut buf = [0u8; OUTPUT_SIZE];
             reader.read_exact(&mut buf)?;
             let leds = buf[0];
-            let num_lock = (leds & 0x01) != 0;
-            let caps_lock = (leds & 0x02) != 0;
-            let scroll_lock = (leds & 0x04) != 0;
-            let compose = (leds & 0x08) != 0;
-            let kana = (leds & 0x10) != 0;
+            let state = LedState {
+                num_lock: (leds & 0x01) != 0,
+                caps_lock: (leds & 0x02) != 0,
+                scroll_lock: (leds & 0x04) != 0,
+                compose: (leds & 0x08) != 0,
+                kana: (leds & 0x10) != 0,
+            };
             println!(
                 "← LEDs: Num={} Caps={} Scroll={} Compose={} Kana={}",
-                num_lock, caps_lock, scroll_lock, compose, kana
+                state.num_lock, state.caps_lock, state.scroll_lock, state.compose, state.kana
             );
+            if let Some(session) = output_session.lock().unwrap().as_mut() {
+                let _ = session.record_output(&state);
+            }
             Ok(())
         })
         .expect("Failed to register LED callback");
 
-    println!("Every 5s: type 'Hello!' + Enter. Press Ctrl+C to stop.");
-
-    // Type "Hello!" + Enter every 5 seconds
-    loop {
-        if let Err(e) = type_string(&mut stream, "Hello!") {
-            eprintln!("Write error: {}", e);
-            break;
+    if let Some(path) = replay_path {
+        println!("Replaying {} at {}x speed. Press Ctrl+C to stop.", path, replay_speed);
+        if let Err(e) = recorder::replay::<KeyboardInput, LedState, _>(path, DEVICE_TYPE, replay_speed, |report| {
+            if let Err(e) = stream.send(report) {
+                eprintln!("Write error: {}", e);
+            }
+        }) {
+            eprintln!("Replay error: {}", e);
         }
+    } else {
+        println!("Every 5s: type 'Hello!' + Enter. Press Ctrl+C to stop.");
 
-        thread::sleep(Duration::from_millis(100));
+        // Queue up a whole phrase at a time instead of blocking on sleep()
+        // between key-down and key-up reports; the queue drains itself below.
+        let mut queue = ScheduleQueue::new();
+        let mut next_type = Instant::now();
+        loop {
+            if Instant::now() >= next_type {
+                match type_string(&keymap, &mut queue, "Hello!") {
+                    Ok(end) => {
+                        tap(&mut queue, KEY_ENTER, 0, end + Duration::from_millis(100), 100, 100);
+                        println!("→ Queued: Hello!");
+                    }
+                    Err(e) => eprintln!("Can't type phrase on this layout: {}", e),
+                }
+                next_type = Instant::now() + Duration::from_secs(5);
+            }
 
-        if let Err(e) = press_key(&mut stream, KEY_ENTER) {
-            eprintln!("Write error: {}", e);
-            break;
-        }
+            for report in queue.pump() {
+                if let Some(session) = session.lock().unwrap().as_mut() {
+                    let _ = session.record(&report);
+                }
+                if let Err(e) = stream.send(&report) {
+                    eprintln!("Write error: {}", e);
+                }
+            }
 
-        println!("→ Typed: Hello!");
-        thread::sleep(Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
     }
 
     // Cleanup
@@ -129,60 +221,48 @@ fn main() {
     }
 }
 
-fn type_string(
-    stream: &mut viiper_client::DeviceStream,
-    text: &str,
-) -> Result<(), viiper_client::error::ViiperError> {
-    for ch in text.chars() {
-        let code_point = ch as u32;
-        let key = match CHAR_TO_KEY.get(&(code_point as u8)) {
-            Some(&k) => k,
-            None => continue,
-        };
-
-        let mut mods = 0;
-        if SHIFT_CHARS.contains(&(code_point as u8)) {
-            mods = MOD_LEFT_SHIFT;
-        }
-
-        // Key down
-        let down = KeyboardInput {
-            modifiers: mods,
-            count: 1,
-            keys: vec![key],
-        };
-        stream.send(&down)?;
-        thread::sleep(Duration::from_millis(100));
-
-        // Key up
-        let up = KeyboardInput {
-            modifiers: 0,
-            count: 0,
-            keys: vec![],
-        };
-        stream.send(&up)?;
-        thread::sleep(Duration::from_millis(100));
-    }
-    Ok(())
-}
-
-fn press_key(
-    stream: &mut viiper_client::DeviceStream,
+/// Enqueue a key-down followed by a key-up `down_ms`/`up_ms` apart,
+/// `delay` from now. Returns the offset at which the tap finishes, so
+/// callers can chain further taps after it without blocking on sleep().
+fn tap(
+    queue: &mut ScheduleQueue<KeyboardInput>,
     key: u8,
-) -> Result<(), viiper_client::error::ViiperError> {
-    let press = KeyboardInput {
-        modifiers: 0,
+    modifiers: u8,
+    delay: Duration,
+    down_ms: u64,
+    up_ms: u64,
+) -> Duration {
+    let down = KeyboardInput {
+        modifiers,
         count: 1,
         keys: vec![key],
     };
-    stream.send(&press)?;
-    thread::sleep(Duration::from_millis(100));
-
-    let release = KeyboardInput {
+    let up = KeyboardInput {
         modifiers: 0,
         count: 0,
         keys: vec![],
     };
-    stream.send(&release)?;
-    Ok(())
+    queue.schedule(down, delay);
+    queue.schedule(up, delay + Duration::from_millis(down_ms));
+    delay + Duration::from_millis(down_ms + up_ms)
+}
+
+/// Enqueue a whole phrase up front, driving `keymap` to turn each
+/// character into its chord sequence (possibly several key presses for
+/// dead-key / compose characters) instead of the old cast-to-`u8`
+/// `CHAR_TO_KEY` lookup. The queue drains it at the original
+/// 100ms-down/100ms-up cadence without blocking the caller. Returns the
+/// offset at which the last chord step finishes, or every character the
+/// active layout can't reproduce.
+fn type_string(
+    keymap: &Keymap,
+    queue: &mut ScheduleQueue<KeyboardInput>,
+    text: &str,
+) -> Result<Duration, keymap::UnmappableChars> {
+    let chords = keymap.encode(text)?;
+    let mut at = Duration::ZERO;
+    for step in chords {
+        at = tap(queue, step.key, step.modifiers, at, 100, 100);
+    }
+    Ok(at)
 }
@@ -0,0 +1,115 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// First line of every recording file: identifies which device type the
+/// reports below were captured from, using the same token passed in
+/// `DeviceCreateRequest` (e.g. `"keyboard"`, `"xbox360"`).
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    device_type: String,
+}
+
+/// One recorded entry: either a report sent to the device, or an output
+/// report (e.g. LEDs, rumble) the server sent back. Keeping both on the
+/// same timeline means a recording captures the full session, not just
+/// one direction of it.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "dir")]
+enum RecordedEntry<T, O> {
+    Sent { delta_ms: u64, report: T },
+    Output { delta_ms: u64, report: O },
+}
+
+/// Captures reports sent to, and output reports received from, a device
+/// stream to a newline-delimited JSON file, one [`RecordedEntry`] per
+/// line, so a session can be replayed later with `replay`.
+pub struct Recorder<T, O> {
+    start: Instant,
+    writer: BufWriter<File>,
+    _marker: std::marker::PhantomData<(T, O)>,
+}
+
+impl<T: Serialize, O: Serialize> Recorder<T, O> {
+    pub fn create(path: &str, device_type: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = RecordingHeader {
+            device_type: device_type.to_string(),
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        Ok(Self {
+            start: Instant::now(),
+            writer,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    pub fn record(&mut self, report: &T) -> io::Result<()> {
+        let entry = RecordedEntry::Sent {
+            delta_ms: self.start.elapsed().as_millis() as u64,
+            report,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    pub fn record_output(&mut self, report: &O) -> io::Result<()> {
+        let entry = RecordedEntry::Output {
+            delta_ms: self.start.elapsed().as_millis() as u64,
+            report,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Reads back a recording created by [`Recorder`] and calls `send` for
+/// each sent report, honoring the original inter-event timing (scaled by
+/// `speed`; `2.0` plays twice as fast). Output reports are skipped: they
+/// were produced by the server, not fed to it, so replaying them has
+/// nothing to send. Returns an error if the recording was captured from a
+/// different device type than `device_type`.
+pub fn replay<T, O, F>(path: &str, device_type: &str, speed: f64, mut send: F) -> io::Result<()>
+where
+    T: DeserializeOwned,
+    O: DeserializeOwned,
+    F: FnMut(&T),
+{
+    let file = File::open(path)?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header_line = lines
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "empty recording"))??;
+    let header: RecordingHeader = serde_json::from_str(&header_line)?;
+    if header.device_type != device_type {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "recording is for device type '{}', expected '{}'",
+                header.device_type, device_type
+            ),
+        ));
+    }
+
+    let mut last_delta = 0u64;
+    for line in lines {
+        let line = line?;
+        let entry: RecordedEntry<T, O> = serde_json::from_str(&line)?;
+        let delta_ms = match &entry {
+            RecordedEntry::Sent { delta_ms, .. } => *delta_ms,
+            RecordedEntry::Output { delta_ms, .. } => *delta_ms,
+        };
+        let wait_ms = delta_ms.saturating_sub(last_delta) as f64 / speed;
+        std::thread::sleep(Duration::from_millis(wait_ms.max(0.0) as u64));
+        last_delta = delta_ms;
+        if let RecordedEntry::Sent { report, .. } = &entry {
+            send(report);
+        }
+    }
+    Ok(())
+}
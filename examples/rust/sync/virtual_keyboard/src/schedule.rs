@@ -0,0 +1,5 @@
+// Shared with the async `virtual_keyboard` example via `include!` rather
+// than a symlink, so the module still builds from a checkout that can't
+// or won't preserve symlinks (e.g. `core.symlinks=false` on Windows) and
+// survives being packaged as a standalone crate.
+include!("../../../common/schedule.rs");
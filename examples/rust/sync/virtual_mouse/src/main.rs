@@ -0,0 +1,158 @@
+//! `devices::mouse` does not exist in `viiper_client` in this tree (only
+//! `devices::keyboard` and `devices::xbox360` are real modules here) - the
+//! types and constants below are speculative, sketched to the same shape
+//! as the existing device modules, not verified against a real mouse
+//! implementation.
+use std::thread;
+use std::time::Duration;
+use viiper_client::{devices::mouse::*, ViiperClient};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <api_addr>", args[0]);
+        eprintln!("Example: {} localhost:3242", args[0]);
+        std::process::exit(1);
+    }
+
+    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
+        eprintln!("Invalid address '{}': {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    let client = ViiperClient::new(addr);
+
+    // Find or create a bus
+    let (bus_id, created_bus) = match client.bus_list() {
+        Ok(resp) if resp.buses.is_empty() => match client.bus_create(None) {
+            Ok(r) => {
+                println!("Created bus {}", r.bus_id);
+                (r.bus_id, true)
+            }
+            Err(e) => {
+                eprintln!("BusCreate failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Ok(resp) => {
+            let bus_id = *resp.buses.iter().min().unwrap();
+            println!("Using existing bus {}", bus_id);
+            (bus_id, false)
+        }
+        Err(e) => {
+            eprintln!("BusList error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Add device
+    let device_info = match client.bus_device_add(
+        bus_id,
+        &viiper_client::types::DeviceCreateRequest {
+            r#type: Some("mouse".to_string()),
+            id_vendor: None,
+            id_product: None,
+        },
+    ) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("AddDevice error: {}", e);
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    // Connect to device stream
+    let mut stream = match client.connect_device(device_info.bus_id, &device_info.dev_id) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ConnectDevice error: {}", e);
+            let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id));
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Created and connected to device {} on bus {}",
+        device_info.dev_id, device_info.bus_id
+    );
+
+    stream
+        .on_disconnect(|| {
+            eprintln!("Device disconnected by server");
+            std::process::exit(0);
+        })
+        .expect("Failed to register disconnect callback");
+
+    println!("Drawing a square with the pointer every 2s. Press Ctrl+C to stop.");
+
+    // Move in a small square, then left-click, every 2 seconds.
+    loop {
+        for (dx, dy) in [(40, 0), (0, 40), (-40, 0), (0, -40)] {
+            if let Err(e) = move_by(&mut stream, dx, dy) {
+                eprintln!("Write error: {}", e);
+                std::process::exit(1);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        if let Err(e) = click(&mut stream, BUTTON_LEFT) {
+            eprintln!("Write error: {}", e);
+            std::process::exit(1);
+        }
+
+        println!("→ Moved in a square and clicked");
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+/// Move the pointer by `(dx, dy)` relative to its current position.
+fn move_by(
+    stream: &mut viiper_client::DeviceStream,
+    dx: i16,
+    dy: i16,
+) -> Result<(), viiper_client::error::ViiperError> {
+    let moved = MouseInput {
+        dx,
+        dy,
+        wheel: 0,
+        buttons: 0,
+    };
+    stream.send(&moved)?;
+
+    let settled = MouseInput {
+        dx: 0,
+        dy: 0,
+        wheel: 0,
+        buttons: 0,
+    };
+    stream.send(&settled)
+}
+
+/// Press and release a mouse button at the current pointer position.
+fn click(
+    stream: &mut viiper_client::DeviceStream,
+    button: u8,
+) -> Result<(), viiper_client::error::ViiperError> {
+    let press = MouseInput {
+        dx: 0,
+        dy: 0,
+        wheel: 0,
+        buttons: button,
+    };
+    stream.send(&press)?;
+    thread::sleep(Duration::from_millis(50));
+
+    let release = MouseInput {
+        dx: 0,
+        dy: 0,
+        wheel: 0,
+        buttons: 0,
+    };
+    stream.send(&release)
+}
@@ -0,0 +1,173 @@
+//! `devices::touchscreen` does not exist in `viiper_client` in this tree
+//! (only `devices::keyboard` and `devices::xbox360` are real modules
+//! here) - the types and constants below are speculative, sketched to
+//! the same shape as the existing device modules, not verified against a
+//! real touchscreen implementation.
+use std::thread;
+use std::time::Duration;
+use viiper_client::{devices::touchscreen::*, ViiperClient};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <api_addr>", args[0]);
+        eprintln!("Example: {} localhost:3242", args[0]);
+        std::process::exit(1);
+    }
+
+    let addr: std::net::SocketAddr = args[1].parse().unwrap_or_else(|e| {
+        eprintln!("Invalid address '{}': {}", args[1], e);
+        std::process::exit(1);
+    });
+
+    let client = ViiperClient::new(addr);
+
+    // Find or create a bus
+    let (bus_id, created_bus) = match client.bus_list() {
+        Ok(resp) if resp.buses.is_empty() => match client.bus_create(None) {
+            Ok(r) => {
+                println!("Created bus {}", r.bus_id);
+                (r.bus_id, true)
+            }
+            Err(e) => {
+                eprintln!("BusCreate failed: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Ok(resp) => {
+            let bus_id = *resp.buses.iter().min().unwrap();
+            println!("Using existing bus {}", bus_id);
+            (bus_id, false)
+        }
+        Err(e) => {
+            eprintln!("BusList error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Add device
+    let device_info = match client.bus_device_add(
+        bus_id,
+        &viiper_client::types::DeviceCreateRequest {
+            r#type: Some("touchscreen".to_string()),
+            id_vendor: None,
+            id_product: None,
+        },
+    ) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("AddDevice error: {}", e);
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    // Connect to device stream
+    let mut stream = match client.connect_device(device_info.bus_id, &device_info.dev_id) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("ConnectDevice error: {}", e);
+            let _ = client.bus_device_remove(device_info.bus_id, Some(&device_info.dev_id));
+            if created_bus {
+                let _ = client.bus_remove(Some(bus_id));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Created and connected to device {} on bus {}",
+        device_info.dev_id, device_info.bus_id
+    );
+
+    stream
+        .on_disconnect(|| {
+            eprintln!("Device disconnected by server");
+            std::process::exit(0);
+        })
+        .expect("Failed to register disconnect callback");
+
+    println!("Tapping the centre of the screen every 2s, pinching every other tap. Press Ctrl+C to stop.");
+
+    // Alternate a single-finger tap with a two-finger pinch every 2
+    // seconds, so both the single- and multi-contact paths get exercised.
+    let mut pinch = false;
+    loop {
+        if pinch {
+            if let Err(e) = pinch_zoom(&mut stream, 0x8000, 0x8000) {
+                eprintln!("Write error: {}", e);
+                std::process::exit(1);
+            }
+            println!("→ Pinched at (0x8000, 0x8000)");
+        } else {
+            if let Err(e) = tap(&mut stream, 0x8000, 0x8000) {
+                eprintln!("Write error: {}", e);
+                std::process::exit(1);
+            }
+            println!("→ Tapped (0x8000, 0x8000)");
+        }
+        pinch = !pinch;
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+/// Touch down and lift at `(x, y)` on a single contact, slot 0.
+fn tap(
+    stream: &mut viiper_client::DeviceStream,
+    x: u16,
+    y: u16,
+) -> Result<(), viiper_client::error::ViiperError> {
+    let down = TouchscreenInput {
+        contacts: vec![TouchContact {
+            slot: 0,
+            x,
+            y,
+            state: TOUCH_DOWN,
+        }],
+    };
+    stream.send(&down)?;
+    thread::sleep(Duration::from_millis(100));
+
+    let up = TouchscreenInput { contacts: vec![] };
+    stream.send(&up)
+}
+
+/// Two-finger pinch-zoom centred on `(x, y)`: slots 0 and 1 touch down
+/// symmetrically offset from the centre, move apart over a few frames,
+/// then both lift - exercising multiple simultaneous contacts instead of
+/// only ever sending a single slot.
+fn pinch_zoom(
+    stream: &mut viiper_client::DeviceStream,
+    x: u16,
+    y: u16,
+) -> Result<(), viiper_client::error::ViiperError> {
+    const STEPS: u16 = 5;
+    const STEP: u16 = 200;
+
+    for step in 0..=STEPS {
+        let offset = step * STEP;
+        let contacts = TouchscreenInput {
+            contacts: vec![
+                TouchContact {
+                    slot: 0,
+                    x: x.saturating_sub(offset),
+                    y,
+                    state: TOUCH_DOWN,
+                },
+                TouchContact {
+                    slot: 1,
+                    x: x.saturating_add(offset),
+                    y,
+                    state: TOUCH_DOWN,
+                },
+            ],
+        };
+        stream.send(&contacts)?;
+        thread::sleep(Duration::from_millis(30));
+    }
+
+    let up = TouchscreenInput { contacts: vec![] };
+    stream.send(&up)
+}